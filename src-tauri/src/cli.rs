@@ -0,0 +1,163 @@
+use crate::formats;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Headless batch mode for scripts, cron jobs, and CI, mirroring the GUI's
+/// decrypt-and-write pipeline without spawning a Tauri window.
+#[derive(Parser)]
+#[command(name = "sonictransmute", about = "Decrypt NCM/QMC/KuWo audio files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decrypt one or more encrypted audio files or directories.
+    Decrypt {
+        /// Files or directories to decrypt.
+        inputs: Vec<PathBuf>,
+
+        /// Write decrypted output into this directory instead of alongside
+        /// each input file.
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+
+        /// Output format: keep the source codec, or transcode via ffmpeg.
+        #[arg(long, value_enum, default_value = "keep")]
+        format: CliFormat,
+
+        /// Recurse into subdirectories when an input is a directory.
+        #[arg(long)]
+        recursive: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CliFormat {
+    Keep,
+    Flac,
+    Mp3,
+}
+
+impl From<CliFormat> for formats::OutputFormat {
+    fn from(format: CliFormat) -> Self {
+        match format {
+            CliFormat::Keep => formats::OutputFormat::KeepOriginal,
+            CliFormat::Flac => formats::OutputFormat::Flac,
+            CliFormat::Mp3 => formats::OutputFormat::Mp3,
+        }
+    }
+}
+
+/// Returns `Some(exit_code)` if the process was launched with CLI arguments
+/// and should run headlessly instead of starting the GUI, `None` if it
+/// should start the GUI as usual (no arguments given).
+pub fn maybe_run() -> Option<i32> {
+    if std::env::args_os().count() <= 1 {
+        return None;
+    }
+    Some(run(Cli::parse()))
+}
+
+fn run(cli: Cli) -> i32 {
+    let Command::Decrypt { inputs, out_dir, format, recursive } = cli.command;
+    let format = formats::OutputFormat::from(format);
+
+    let files = collect_input_files(&inputs, recursive);
+    if files.is_empty() {
+        eprintln!("No supported encrypted audio files found in the given inputs.");
+        return 1;
+    }
+
+    let mut succeeded = 0usize;
+    let mut failed = 0usize;
+
+    for file in &files {
+        // A crafted/corrupt input can make the decryptor panic rather than
+        // return an error. Isolate
+        // each file so one bad input degrades to a reported FAIL line
+        // instead of aborting the rest of the batch.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            decrypt_one(file, format, out_dir.as_deref())
+        }));
+
+        match result {
+            Ok(Ok(output_path)) => {
+                println!("OK   {} -> {}", file.display(), output_path.display());
+                succeeded += 1;
+            }
+            Ok(Err(e)) => {
+                println!("FAIL {}: {}", file.display(), e);
+                failed += 1;
+            }
+            Err(_) => {
+                println!("FAIL {}: decryptor panicked on malformed input", file.display());
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\n{} converted, {} failed, {} total",
+        succeeded,
+        failed,
+        files.len()
+    );
+
+    if failed > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Expand `inputs` (files and/or directories) into the flat list of
+/// supported encrypted files to decrypt.
+fn collect_input_files(inputs: &[PathBuf], recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        if input.is_file() {
+            if formats::is_supported_extension(input) {
+                files.push(input.clone());
+            } else {
+                eprintln!("Skipping unsupported file: {}", input.display());
+            }
+            continue;
+        }
+
+        if !input.is_dir() {
+            eprintln!("Skipping missing input: {}", input.display());
+            continue;
+        }
+
+        let walker = WalkDir::new(input).max_depth(if recursive { usize::MAX } else { 1 });
+        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+            if entry.file_type().is_file() && formats::is_supported_extension(entry.path()) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    files
+}
+
+/// Decrypt a single file, writing its output alongside the input (or into
+/// `out_dir` if one was given).
+fn decrypt_one(path: &Path, format: formats::OutputFormat, out_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let decrypted = formats::decrypt_any(path, &|_, _| {})?;
+    let written = formats::write_output(path, decrypted, format, &|_| {})?;
+
+    let Some(out_dir) = out_dir else { return Ok(written) };
+
+    fs::create_dir_all(out_dir)?;
+    let file_name = written
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("decrypted output has no file name: {:?}", written))?;
+    let target = out_dir.join(file_name);
+    fs::rename(&written, &target)?;
+    Ok(target)
+}
@@ -0,0 +1,52 @@
+use super::{AudioDecryptor, DecryptedAudio, ProgressFn, PROGRESS_CHUNK_SIZE};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Magic bytes at the start of every KuWo `.kwm` container.
+const KWM_MAGIC: &[u8] = b"yeelion-kuwo-tme";
+
+/// Static per-byte key KuWo uses to XOR-obfuscate the audio stream,
+/// cycled across the payload the same way the NCM S-box RC4 cycles its key.
+const KUWO_KEY: &[u8] = b"MoOtOiTvINGwd2E6n0E1i7L5t2";
+
+/// KuWo Music `.kwm` container.
+pub struct KuwoDecryptor;
+
+impl AudioDecryptor for KuwoDecryptor {
+    fn sniff(path: &Path) -> bool {
+        if path.extension().and_then(|e| e.to_str()) != Some("kwm") {
+            return false;
+        }
+        match fs::read(path) {
+            Ok(data) => data.len() >= KWM_MAGIC.len() && &data[..KWM_MAGIC.len()] == KWM_MAGIC,
+            Err(_) => false,
+        }
+    }
+
+    fn decrypt(path: &Path, progress: &ProgressFn) -> Result<DecryptedAudio> {
+        let data = fs::read(path).context("Failed to read KuWo file")?;
+        if data.len() < KWM_MAGIC.len() || &data[..KWM_MAGIC.len()] != KWM_MAGIC {
+            return Err(anyhow!("Invalid KuWo file format"));
+        }
+
+        // Header occupies the first 0x400 bytes; audio data follows.
+        let header_len = 0x400.min(data.len());
+        let mut audio = data[header_len..].to_vec();
+        let total = audio.len() as u64;
+
+        for (chunk_start, chunk) in audio.chunks_mut(PROGRESS_CHUNK_SIZE).enumerate() {
+            let base = chunk_start * PROGRESS_CHUNK_SIZE;
+            for (offset, byte) in chunk.iter_mut().enumerate() {
+                *byte ^= KUWO_KEY[(base + offset) % KUWO_KEY.len()];
+            }
+            progress((base + chunk.len()) as u64, total);
+        }
+
+        Ok(DecryptedAudio {
+            data: audio,
+            metadata: None,
+            cover_art: None,
+        })
+    }
+}
@@ -0,0 +1,375 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use symphonia::core::codecs::{CODEC_TYPE_AAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_VORBIS};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::probe::Hint;
+
+pub mod kuwo;
+pub mod ncm;
+pub mod qmc;
+
+/// Byte-offset/total callback a decryptor reports progress through while
+/// it works, e.g. after each chunk of an RC4/XOR pass.
+pub type ProgressFn<'a> = dyn Fn(u64, u64) + Send + Sync + 'a;
+
+/// Size of the chunks decryptors process between progress callbacks.
+pub const PROGRESS_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Track metadata recovered from an encrypted container, if the format
+/// embeds any. NCM carries title/artist/album inline; QMC and KuWo
+/// containers do not expose tags today, so their decryptors leave this
+/// `None`.
+#[derive(Debug, Default, Clone)]
+pub struct TrackMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// The result of decrypting a supported container: the raw audio stream
+/// plus whatever metadata and cover art could be recovered alongside it.
+pub struct DecryptedAudio {
+    pub data: Vec<u8>,
+    pub metadata: Option<TrackMetadata>,
+    pub cover_art: Option<Vec<u8>>,
+}
+
+/// A decryptor for one encrypted audio container format.
+///
+/// Implementations are zero-sized marker types (e.g. `NcmDecryptor`);
+/// `sniff`/`decrypt` are associated functions rather than `&self` methods,
+/// so dispatch in [`decrypt_any`] stays a plain static match instead of
+/// requiring trait objects.
+pub trait AudioDecryptor {
+    /// Returns true if `path` looks like this format, by extension and/or
+    /// magic bytes.
+    fn sniff(path: &Path) -> bool;
+
+    /// Decrypt the file at `path` into its underlying audio stream,
+    /// reporting `(bytes_done, bytes_total)` through `progress` as it works.
+    fn decrypt(path: &Path, progress: &ProgressFn) -> Result<DecryptedAudio>;
+}
+
+/// Try every supported decryptor against `path` and decrypt with the first
+/// one that recognizes it.
+pub fn decrypt_any(path: &Path, progress: &ProgressFn) -> Result<DecryptedAudio> {
+    if ncm::NcmDecryptor::sniff(path) {
+        ncm::NcmDecryptor::decrypt(path, progress)
+    } else if qmc::QmcDecryptor::sniff(path) {
+        qmc::QmcDecryptor::decrypt(path, progress)
+    } else if kuwo::KuwoDecryptor::sniff(path) {
+        kuwo::KuwoDecryptor::decrypt(path, progress)
+    } else if is_unsupported_qmcv2_extension(path) {
+        Err(anyhow!(
+            "{:?} is a QMCv2 container (.mflac/.mgg); QMCv2's per-file ekey decryption isn't supported yet",
+            path
+        ))
+    } else {
+        Err(anyhow!("Unsupported or unrecognized encrypted audio format: {:?}", path))
+    }
+}
+
+/// True for QMCv2's `.mflac`/`.mgg` extensions, which [`qmc::QmcDecryptor`]
+/// deliberately does not claim (see its doc comment). Kept separate from
+/// [`is_supported_extension`] so callers can tell "known format we can't
+/// decrypt yet" apart from "not an encrypted audio file at all".
+fn is_unsupported_qmcv2_extension(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("mflac") | Some("mgg"))
+}
+
+/// Returns true if `path`'s extension is one of the encrypted containers we
+/// know how to decrypt. Used by the watcher/CLI to filter candidate files
+/// without doing a full magic-byte sniff on every entry.
+///
+/// QMCv2's `.mflac`/`.mgg` extensions deliberately count as "supported"
+/// here even though decryption for them isn't implemented: letting them
+/// through to [`decrypt_any`] surfaces a clear "QMCv2 not supported" error
+/// for the user instead of silently vanishing from the file list as if they
+/// were never encrypted audio in the first place.
+pub fn is_supported_extension(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("ncm") | Some("qmc0") | Some("qmc3") | Some("kwm")
+    ) || is_unsupported_qmcv2_extension(path)
+}
+
+/// The real codec of a decrypted audio stream, identified by probing its
+/// container rather than guessing from a single magic-byte check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedCodec {
+    Mp3,
+    Flac,
+    Aac,
+    Ogg,
+    Unknown,
+}
+
+impl DetectedCodec {
+    /// The file extension this codec should be written out with when kept
+    /// in its original format.
+    fn extension(self) -> &'static str {
+        match self {
+            DetectedCodec::Mp3 => "mp3",
+            DetectedCodec::Flac => "flac",
+            DetectedCodec::Aac => "m4a",
+            DetectedCodec::Ogg => "ogg",
+            DetectedCodec::Unknown => "mp3",
+        }
+    }
+}
+
+/// Probe a decrypted byte buffer with `symphonia` to identify its real
+/// codec, instead of trusting a single magic-byte check.
+pub fn detect_codec(data: &[u8]) -> DetectedCodec {
+    let cursor = Cursor::new(data.to_vec());
+    let mss = MediaSourceStream::new(Box::new(cursor), Default::default());
+
+    let probed = symphonia::default::get_probe().format(
+        &Hint::new(),
+        mss,
+        &Default::default(),
+        &Default::default(),
+    );
+
+    let Ok(mut probed) = probed else { return DetectedCodec::Unknown };
+    let Some(track) = probed.format.default_track() else { return DetectedCodec::Unknown };
+
+    match track.codec_params.codec {
+        CODEC_TYPE_MP3 => DetectedCodec::Mp3,
+        CODEC_TYPE_FLAC => DetectedCodec::Flac,
+        CODEC_TYPE_AAC => DetectedCodec::Aac,
+        CODEC_TYPE_VORBIS => DetectedCodec::Ogg,
+        _ => DetectedCodec::Unknown,
+    }
+}
+
+/// How [`write_output`] should handle the decrypted audio stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Write the decrypted stream as-is, under the extension matching its
+    /// real (probed) codec. No transcode, no ffmpeg dependency. This is the
+    /// common case, since NCM/QMC/KuWo payloads are already MP3 or FLAC.
+    /// A stream the probe cannot identify is written with the `.mp3`
+    /// extension.
+    #[default]
+    KeepOriginal,
+    /// Output FLAC: transcode via ffmpeg, unless the probed source is
+    /// already FLAC, in which case it is written as-is.
+    Flac,
+    /// Output MP3: transcode via ffmpeg, unless the probed source is
+    /// already MP3, in which case it is written as-is.
+    Mp3,
+}
+
+/// Approximate duration of a decrypted audio stream in seconds, used to
+/// turn ffmpeg's `out_time_ms=` progress lines into a percentage.
+fn probe_duration_secs(data: &[u8]) -> Option<f64> {
+    let cursor = Cursor::new(data.to_vec());
+    let mss = MediaSourceStream::new(Box::new(cursor), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&Hint::new(), mss, &Default::default(), &Default::default())
+        .ok()?;
+    let track = probed.format.default_track()?;
+    let n_frames = track.codec_params.n_frames?;
+    let sample_rate = track.codec_params.sample_rate? as f64;
+    if sample_rate == 0.0 {
+        return None;
+    }
+    Some(n_frames as f64 / sample_rate)
+}
+
+/// Write a decrypted container out to disk: probe its real codec with
+/// `symphonia`, write it as-is (or transcode through ffmpeg only if the
+/// caller explicitly asked for a different format), then embed any
+/// recovered tags and cover art. Returns the path of the final file.
+///
+/// `progress` is called with a 0-100 percentage while an ffmpeg transcode
+/// is running; the "keep original" path completes synchronously so it has
+/// no intermediate progress to report.
+pub fn write_output(
+    original_path: &Path,
+    decrypted: DecryptedAudio,
+    format: OutputFormat,
+    progress: &dyn Fn(u8),
+) -> Result<PathBuf> {
+    let DecryptedAudio { data: audio_data, metadata, cover_art } = decrypted;
+    let codec = detect_codec(&audio_data);
+
+    let target_ext = match format {
+        OutputFormat::KeepOriginal => codec.extension(),
+        OutputFormat::Flac => "flac",
+        OutputFormat::Mp3 => "mp3",
+    };
+
+    let needs_transcode = match format {
+        OutputFormat::KeepOriginal => false,
+        OutputFormat::Flac => codec != DetectedCodec::Flac,
+        OutputFormat::Mp3 => codec != DetectedCodec::Mp3,
+    };
+
+    let final_path = original_path.with_extension(target_ext);
+
+    if !needs_transcode {
+        fs::write(&final_path, &audio_data)?;
+    } else {
+        let duration_secs = probe_duration_secs(&audio_data);
+        let temp_path = original_path.with_extension(format!("temp.{}", codec.extension()));
+        fs::write(&temp_path, &audio_data)?;
+
+        let result = transcode_with_ffmpeg(&temp_path, &final_path, duration_secs, progress);
+        let _ = fs::remove_file(&temp_path);
+        result?;
+    }
+
+    let is_flac = target_ext == "flac";
+    let is_mp3 = target_ext == "mp3";
+    if (metadata.is_some() || cover_art.is_some()) && (is_flac || is_mp3) {
+        if let Err(e) = write_tags(&final_path, is_flac, metadata.as_ref(), cover_art.as_deref()) {
+            eprintln!("Failed to write tags to {:?}: {}", final_path, e);
+        }
+    }
+
+    Ok(final_path)
+}
+
+/// Run ffmpeg on `temp_path`, producing `final_path`, reporting 0-100
+/// progress by parsing its `-progress pipe:1` / `out_time_ms=` output
+/// against the source's known `duration_secs` (when it could be probed).
+fn transcode_with_ffmpeg(
+    temp_path: &Path,
+    final_path: &Path,
+    duration_secs: Option<f64>,
+    progress: &dyn Fn(u8),
+) -> Result<()> {
+    let mut child = Command::new("ffmpeg")
+        .args(&[
+            "-y",
+            "-i", temp_path.to_str().unwrap(),
+            "-progress", "pipe:1",
+            "-nostats",
+            final_path.to_str().unwrap(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| anyhow!("FFmpeg not found. Please install FFmpeg and add to PATH."))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            let Some(out_time_ms) = line.strip_prefix("out_time_ms=") else { continue };
+            let (Ok(out_time_ms), Some(duration_secs)) = (out_time_ms.trim().parse::<f64>(), duration_secs) else { continue };
+            if duration_secs <= 0.0 {
+                continue;
+            }
+            let pct = ((out_time_ms / 1_000_000.0 / duration_secs) * 100.0).clamp(0.0, 100.0);
+            progress(pct as u8);
+        }
+    }
+
+    let status = child.wait().context("Failed to wait on ffmpeg process")?;
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut handle) = child.stderr.take() {
+            use std::io::Read;
+            let _ = handle.read_to_string(&mut stderr);
+        }
+        return Err(anyhow!("FFmpeg conversion failed: {}", stderr));
+    }
+
+    progress(100);
+    Ok(())
+}
+
+/// Write recovered title/artist/album tags and cover art into the decrypted
+/// output file: Vorbis comments + a `METADATA_BLOCK_PICTURE` for FLAC via
+/// `metaflac`, or ID3v2 for MP3 via the `id3` crate.
+fn write_tags(
+    final_path: &Path,
+    is_flac: bool,
+    metadata: Option<&TrackMetadata>,
+    cover_art: Option<&[u8]>,
+) -> Result<()> {
+    if is_flac {
+        write_flac_tags(final_path, metadata, cover_art)
+    } else {
+        write_mp3_tags(final_path, metadata, cover_art)
+    }
+}
+
+fn write_flac_tags(final_path: &Path, metadata: Option<&TrackMetadata>, cover_art: Option<&[u8]>) -> Result<()> {
+    if let Some(metadata) = metadata {
+        let mut args: Vec<String> = Vec::new();
+        if let Some(title) = &metadata.title {
+            args.push(format!("--set-tag=TITLE={}", title));
+        }
+        if let Some(artist) = &metadata.artist {
+            args.push(format!("--set-tag=ARTIST={}", artist));
+        }
+        if let Some(album) = &metadata.album {
+            args.push(format!("--set-tag=ALBUM={}", album));
+        }
+        if !args.is_empty() {
+            let status = Command::new("metaflac")
+                .args(&args)
+                .arg(final_path)
+                .output()
+                .context("Failed to run metaflac (is it installed and on PATH?)")?;
+            if !status.status.success() {
+                return Err(anyhow!("metaflac tagging failed: {:?}", String::from_utf8_lossy(&status.stderr)));
+            }
+        }
+    }
+
+    if let Some(image_data) = cover_art {
+        let cover_path = final_path.with_extension("cover.tmp");
+        fs::write(&cover_path, image_data)?;
+        let status = Command::new("metaflac")
+            .arg(format!("--import-picture-from={}", cover_path.to_str().unwrap()))
+            .arg(final_path)
+            .output();
+        let _ = fs::remove_file(&cover_path);
+        match status {
+            Ok(output) if !output.status.success() => {
+                return Err(anyhow!("metaflac cover import failed: {:?}", String::from_utf8_lossy(&output.stderr)));
+            }
+            Err(e) => return Err(anyhow!("Failed to run metaflac for cover art: {}", e)),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn write_mp3_tags(final_path: &Path, metadata: Option<&TrackMetadata>, cover_art: Option<&[u8]>) -> Result<()> {
+    let mut tag = id3::Tag::new();
+
+    if let Some(metadata) = metadata {
+        if let Some(title) = &metadata.title {
+            tag.set_title(title);
+        }
+        if let Some(artist) = &metadata.artist {
+            tag.set_artist(artist);
+        }
+        if let Some(album) = &metadata.album {
+            tag.set_album(album);
+        }
+    }
+
+    if let Some(image_data) = cover_art {
+        let mime_type = if image_data.starts_with(b"\x89PNG") { "image/png" } else { "image/jpeg" };
+        tag.add_frame(id3::frame::Picture {
+            mime_type: mime_type.to_string(),
+            picture_type: id3::frame::PictureType::CoverFront,
+            description: String::new(),
+            data: image_data.to_vec(),
+        });
+    }
+
+    tag.write_to_path(final_path, id3::Version::Id3v24)
+        .context("Failed to write ID3v2 tags")?;
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use super::{AudioDecryptor, DecryptedAudio, ProgressFn, TrackMetadata, PROGRESS_CHUNK_SIZE};
+use anyhow::{anyhow, Context, Result};
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyInit};
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+type Aes128EcbDec = ecb::Decryptor<aes::Aes128>;
+
+// Core Keys for NCM Decryption
+const CORE_KEY: &[u8] = b"\x68\x7A\x48\x52\x41\x6D\x73\x6F\x35\x6B\x49\x6E\x62\x61\x78\x57";
+const MODIFY_KEY: &[u8] = b"\x23\x31\x34\x6C\x6A\x6B\x5F\x21\x5C\x5D\x26\x30\x55\x3C\x27\x28";
+
+// Prefix stripped from the modify-key payload before base64 decoding.
+const MODIFY_KEY_PREFIX: &[u8] = b"163 key(Don't modify):";
+
+/// NetEase Cloud Music `.ncm` container.
+pub struct NcmDecryptor;
+
+impl AudioDecryptor for NcmDecryptor {
+    fn sniff(path: &Path) -> bool {
+        path.extension().and_then(|e| e.to_str()) == Some("ncm")
+    }
+
+    fn decrypt(path: &Path, progress: &ProgressFn) -> Result<DecryptedAudio> {
+        process_ncm(path, progress)
+    }
+}
+
+/// Track metadata embedded in the NCM container's JSON metadata block.
+#[derive(Debug, Deserialize)]
+struct NcmMetadata {
+    #[serde(rename = "musicName")]
+    music_name: Option<String>,
+    artist: Option<serde_json::Value>,
+    album: Option<String>,
+    #[serde(rename = "albumPic")]
+    album_pic: Option<String>,
+}
+
+fn process_ncm(path: &Path, progress: &ProgressFn) -> Result<DecryptedAudio> {
+    if !path.exists() {
+        return Err(anyhow!("File not found"));
+    }
+
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let mut reader = Cursor::new(&buffer);
+
+    // 1. Validate Header
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    let magic_u64 = u64::from_le_bytes(magic);
+    if magic_u64 != 0x4d4144464e455443 { // "CTENFDAM"
+        return Err(anyhow!("Invalid NCM file format"));
+    }
+
+    // Skip 2 bytes gap
+    reader.set_position(reader.position() + 2);
+
+    // 2. Read Key
+    let key_len = reader.read_u32::<LittleEndian>()?;
+    let mut key_data = vec![0u8; key_len as usize];
+    reader.read_exact(&mut key_data)?;
+
+    // Decrypt Key with CORE_KEY
+    for i in 0..key_len as usize {
+        key_data[i] ^= 0x64;
+    }
+
+    let decrypted_key = decrypt_aes(&key_data, CORE_KEY)?;
+    // Remove "neteasecloudmusic" prefix (17 chars)
+    let rc4_key_data = decrypted_key
+        .get(17..)
+        .ok_or_else(|| anyhow!("NCM key block is shorter than the expected \"neteasecloudmusic\" prefix"))?;
+    if rc4_key_data.is_empty() {
+        return Err(anyhow!("NCM key block has no RC4 key data after the prefix"));
+    }
+    let s_box = build_sbox(rc4_key_data);
+
+    // 3. Read Metadata
+    let meta_len = reader.read_u32::<LittleEndian>()?;
+    let metadata = if meta_len > 0 {
+        let mut meta_data = vec![0u8; meta_len as usize];
+        reader.read_exact(&mut meta_data)?;
+        match parse_ncm_metadata(&meta_data) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                eprintln!("Failed to parse NCM metadata: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // 4. Skip CRC (4 bytes) & Gap (5 bytes)
+    reader.set_position(reader.position() + 9);
+
+    // 5. Read Image
+    let img_len = reader.read_u32::<LittleEndian>()?;
+    let cover_art = if img_len > 0 {
+        let mut image_data = vec![0u8; img_len as usize];
+        reader.read_exact(&mut image_data)?;
+        Some(image_data)
+    } else {
+        None
+    };
+
+    // 6. Decrypt Audio Data
+    let audio_start = reader.position() as usize;
+    let mut audio_data = buffer[audio_start..].to_vec();
+    let total = audio_data.len() as u64;
+
+    // Apply RC4 (Custom NCM variant), reporting progress every chunk so a
+    // large file doesn't look stalled at 0% for its whole decryption pass.
+    // Re-implementation of RC4 pseudo-random generation stage for NCM
+    // Note: Standard RC4 PRGA is slightly different, NCM uses a specific S-box mapping
+    // But actually, the key generation above prepares the S-Box.
+    // The application logic:
+    for (chunk_start, chunk) in audio_data.chunks_mut(PROGRESS_CHUNK_SIZE).enumerate() {
+        let base = chunk_start * PROGRESS_CHUNK_SIZE;
+        for (offset, byte) in chunk.iter_mut().enumerate() {
+            let i = base + offset;
+            let j = (i + 1) & 0xff;
+            *byte ^= s_box[s_box[j] as usize]; // Simplified NCM XOR logic
+        }
+        progress((base + chunk.len()) as u64, total);
+    }
+
+    Ok(DecryptedAudio {
+        data: audio_data,
+        metadata: metadata.map(|m| TrackMetadata {
+            title: m.music_name,
+            artist: m.artist.as_ref().and_then(format_artist),
+            album: m.album,
+        }),
+        cover_art,
+    })
+}
+
+/// Decode the NCM metadata block into the embedded JSON track info.
+///
+/// The block is XORed with `0x63`, has a `163 key(Don't modify):` prefix,
+/// and the remainder is base64 then AES-128-ECB (with `MODIFY_KEY`) encoded
+/// JSON prefixed with a `music:` token.
+fn parse_ncm_metadata(meta_data: &[u8]) -> Result<NcmMetadata> {
+    let xored: Vec<u8> = meta_data.iter().map(|b| b ^ 0x63).collect();
+    let xored = std::str::from_utf8(&xored).context("NCM metadata block is not valid UTF-8")?;
+    let encoded = xored
+        .strip_prefix(std::str::from_utf8(MODIFY_KEY_PREFIX)?)
+        .ok_or_else(|| anyhow!("NCM metadata block is missing the expected key prefix"))?;
+
+    let ciphertext = BASE64_STANDARD
+        .decode(encoded.trim())
+        .context("Failed to base64-decode NCM metadata")?;
+    let decrypted = decrypt_aes(&ciphertext, MODIFY_KEY)?;
+    let json_str = std::str::from_utf8(&decrypted).context("NCM metadata JSON is not valid UTF-8")?;
+    let json_str = json_str.strip_prefix("music:").unwrap_or(json_str);
+
+    serde_json::from_str(json_str).context("Failed to parse NCM metadata JSON")
+}
+
+/// Flatten the NCM `artist` field (an array of `[name, id]` pairs) into a
+/// single display string such as "Artist A/Artist B".
+fn format_artist(artist: &serde_json::Value) -> Option<String> {
+    let names: Vec<String> = artist
+        .as_array()?
+        .iter()
+        .filter_map(|entry| entry.as_array()?.first()?.as_str().map(String::from))
+        .collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join("/"))
+    }
+}
+
+fn decrypt_aes(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    // NCM uses AES-128-ECB with PKCS7 padding
+    let dec: Aes128EcbDec = Aes128EcbDec::new_from_slice(key).context("Invalid key length")?;
+    let mut buffer = data.to_vec();
+    let decrypted = dec.decrypt_padded_mut::<Pkcs7>(&mut buffer)
+        .map_err(|e| anyhow!("AES decryption failed: {:?}", e))?;
+    Ok(decrypted.to_vec())
+}
+
+fn build_sbox(key: &[u8]) -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    for i in 0..256 {
+        sbox[i] = i as u8;
+    }
+
+    // NCM Specific S-Box scrambling
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(sbox[i]).wrapping_add(key[i % key.len()]);
+        sbox.swap(i, j as usize);
+    }
+
+    // NCM specific post-processing for the generation box
+    let mut final_sbox = [0u8; 256];
+    for i in 0..256 {
+        let original = sbox[i];
+        let j = sbox[(i + original as usize) & 0xff].wrapping_add(original);
+        final_sbox[i] = sbox[j as usize];
+    }
+
+    final_sbox
+}
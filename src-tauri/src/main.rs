@@ -1,37 +1,68 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod ncm;
+mod cli;
+mod formats;
+mod queue;
 mod watcher;
 
+use queue::ConversionQueue;
 use tauri::{Emitter, Manager, State};
-use std::sync::Mutex;
 use std::path::Path;
-use watcher::FolderWatcher;
+use std::sync::Mutex;
+use watcher::{FolderWatcher, WatcherConfig};
+
+/// Number of files the conversion queue will decrypt/transcode at once.
+const DEFAULT_QUEUE_CONCURRENCY: usize = 4;
 
 struct AppState {
     watcher: Mutex<Option<FolderWatcher>>,
+    conversion_queue: ConversionQueue,
 }
 
-#[tauri::command]
-async fn convert_ncm_file(app: tauri::AppHandle, id: String, file_path: String) -> Result<(), String> {
-    // Notify Frontend: Processing started
+fn parse_output_format(format: Option<&str>) -> Result<formats::OutputFormat, String> {
+    match format {
+        None | Some("keep") => Ok(formats::OutputFormat::KeepOriginal),
+        Some("flac") => Ok(formats::OutputFormat::Flac),
+        Some("mp3") => Ok(formats::OutputFormat::Mp3),
+        Some(other) => Err(format!("Unknown output format: {}", other)),
+    }
+}
+
+fn emit_conversion_progress(app: &tauri::AppHandle, id: &str, progress: u8, status: &str) {
     let _ = app.emit("app:conversion-progress", serde_json::json!({
         "id": id,
-        "progress": 0,
-        "status": "processing"
+        "progress": progress,
+        "status": status
     }));
+}
+
+/// Decrypt and write one file, emitting real `app:conversion-progress`
+/// events as it goes: 0-70% while the decryptor works through the file in
+/// chunks, 70-100% while an (optional) ffmpeg transcode runs.
+pub(crate) fn run_conversion(app: &tauri::AppHandle, id: &str, file_path: &str, format: formats::OutputFormat) -> Result<(), String> {
+    emit_conversion_progress(app, id, 0, "processing");
 
-    // Perform the heavy lifting
-    let result = ncm::process_ncm(&file_path);
+    let decrypt_app = app.clone();
+    let decrypt_id = id.to_string();
+    let decrypt_progress = move |done: u64, total: u64| {
+        let pct = if total == 0 { 0 } else { ((done as f64 / total as f64) * 70.0) as u8 };
+        emit_conversion_progress(&decrypt_app, &decrypt_id, pct, "processing");
+    };
+
+    let transcode_app = app.clone();
+    let transcode_id = id.to_string();
+    let transcode_progress = move |pct: u8| {
+        let scaled = 70 + (pct as u32 * 30 / 100) as u8;
+        emit_conversion_progress(&transcode_app, &transcode_id, scaled, "processing");
+    };
+
+    let result = formats::decrypt_any(Path::new(file_path), &decrypt_progress)
+        .and_then(|decrypted| formats::write_output(Path::new(file_path), decrypted, format, &transcode_progress));
 
     match result {
         Ok(_) => {
-            let _ = app.emit("app:conversion-progress", serde_json::json!({
-                "id": id,
-                "progress": 100,
-                "status": "completed"
-            }));
+            emit_conversion_progress(app, id, 100, "completed");
             Ok(())
         }
         Err(e) => {
@@ -48,23 +79,44 @@ async fn convert_ncm_file(app: tauri::AppHandle, id: String, file_path: String)
 }
 
 #[tauri::command]
-async fn start_folder_watcher(app: tauri::AppHandle, state: State<'_, AppState>, path: String) -> Result<(), String> {
+async fn convert_ncm_file(state: State<'_, AppState>, id: String, file_path: String, format: Option<String>) -> Result<(), String> {
+    let output_format = parse_output_format(format.as_deref())?;
+    state.conversion_queue.push(id, file_path, output_format);
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_folder_watcher(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    recursive: Option<bool>,
+    include_existing: Option<bool>,
+    debounce_ms: Option<u64>,
+) -> Result<(), String> {
     let mut watcher_guard = state.watcher.lock().map_err(|e| e.to_string())?;
-    
+
     // Stop existing watcher if any
     if let Some(watcher) = watcher_guard.take() {
         drop(watcher);
     }
 
+    let defaults = WatcherConfig::default();
+    let config = WatcherConfig {
+        recursive: recursive.unwrap_or(defaults.recursive),
+        include_existing: include_existing.unwrap_or(defaults.include_existing),
+        debounce_ms: debounce_ms.unwrap_or(defaults.debounce_ms),
+    };
+
     let app_handle = app.clone();
-    let new_watcher = FolderWatcher::new(path, move |file_path| {
-        // When a new .ncm file is detected, notify frontend to add it to queue
-        // The frontend 'isWatching' logic will then trigger conversion automatically
-        // Alternatively, we can just emit an event saying "File Added"
-        println!("New file detected: {:?}", file_path);
-        // We emit a custom event that Frontend listens to
-        // Note: For simplicity in this demo, the frontend polls/scans or user re-adds. 
-        // But to fully automate, the frontend needs to listen to "app:file-detected"
+    let new_watcher = FolderWatcher::new(path, config, move |file_path| {
+        let _ = app_handle.emit("app:file-detected", serde_json::json!({
+            "path": file_path,
+        }));
+        if let Some(path_str) = file_path.to_str().map(str::to_string) {
+            let state: State<'_, AppState> = app_handle.state();
+            state.conversion_queue.push(path_str.clone(), path_str, formats::OutputFormat::KeepOriginal);
+        }
     }).map_err(|e| e.to_string())?;
 
     *watcher_guard = Some(new_watcher);
@@ -79,12 +131,25 @@ async fn stop_folder_watcher(state: State<'_, AppState>) -> Result<(), String> {
 }
 
 fn main() {
+    // When launched with CLI arguments (e.g. `sonictransmute decrypt ...`),
+    // run the headless batch job and exit instead of opening the GUI.
+    if let Some(exit_code) = cli::maybe_run() {
+        std::process::exit(exit_code);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(AppState { watcher: Mutex::new(None) })
+        .setup(|app| {
+            let conversion_queue = ConversionQueue::new(app.handle().clone(), DEFAULT_QUEUE_CONCURRENCY);
+            app.manage(AppState {
+                watcher: Mutex::new(None),
+                conversion_queue,
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
-            convert_ncm_file, 
-            start_folder_watcher, 
+            convert_ncm_file,
+            start_folder_watcher,
             stop_folder_watcher
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,104 @@
+use crate::formats;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+/// One decryption/conversion job submitted to the [`ConversionQueue`].
+pub struct ConversionJob {
+    pub id: String,
+    pub file_path: String,
+    pub format: formats::OutputFormat,
+}
+
+/// Counters shared across the worker pool, used to emit `app:batch-progress`
+/// summaries as jobs complete.
+struct BatchStats {
+    total: AtomicUsize,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+/// A bounded, concurrency-limited pool of workers that convert queued
+/// files. `concurrency` workers pull from the same channel, so at most
+/// that many files are being decrypted/transcoded at once; the rest wait
+/// in the channel's buffer instead of all running (and thrashing disk/CPU)
+/// at once.
+pub struct ConversionQueue {
+    sender: Sender<ConversionJob>,
+    stats: Arc<BatchStats>,
+}
+
+impl ConversionQueue {
+    pub fn new(app: AppHandle, concurrency: usize) -> Self {
+        let (sender, receiver): (Sender<ConversionJob>, Receiver<ConversionJob>) = bounded(256);
+        let stats = Arc::new(BatchStats {
+            total: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        });
+
+        for _ in 0..concurrency.max(1) {
+            let receiver = receiver.clone();
+            let app = app.clone();
+            let stats = stats.clone();
+            thread::spawn(move || {
+                for job in receiver.iter() {
+                    let start = Instant::now();
+
+                    // A malformed input can make the decryptor panic rather
+                    // than return an error. Catch it here so the job is
+                    // reported as a normal failure instead of silently
+                    // taking this worker thread down (which would leave the
+                    // job stuck at "processing" forever and permanently
+                    // shrink the pool).
+                    let app_for_job = app.clone();
+                    let job_for_panic = (job.id.clone(), job.file_path.clone());
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        crate::run_conversion(&app_for_job, &job.id, &job.file_path, job.format)
+                    }))
+                    .unwrap_or_else(|_| {
+                        let (id, file_path) = job_for_panic;
+                        eprintln!("Conversion worker panicked on {}", file_path);
+                        let _ = app.emit("app:conversion-progress", serde_json::json!({
+                            "id": id,
+                            "progress": 0,
+                            "status": "error",
+                            "message": "Decryption panicked on malformed input",
+                        }));
+                        Err("panicked".to_string())
+                    });
+
+                    let elapsed = start.elapsed().as_secs_f64().max(0.001);
+
+                    if result.is_ok() {
+                        stats.completed.fetch_add(1, Ordering::SeqCst);
+                    } else {
+                        stats.failed.fetch_add(1, Ordering::SeqCst);
+                    }
+
+                    let completed = stats.completed.load(Ordering::SeqCst);
+                    let failed = stats.failed.load(Ordering::SeqCst);
+                    let total = stats.total.load(Ordering::SeqCst);
+                    let _ = app.emit("app:batch-progress", serde_json::json!({
+                        "completed": completed,
+                        "failed": failed,
+                        "total": total,
+                        "throughput_files_per_sec": 1.0 / elapsed,
+                    }));
+                }
+            });
+        }
+
+        ConversionQueue { sender, stats }
+    }
+
+    /// Enqueue a file for conversion. Blocks only if the channel's buffer is
+    /// full, which in practice means the pool is badly backlogged.
+    pub fn push(&self, id: String, file_path: String, format: formats::OutputFormat) {
+        self.stats.total.fetch_add(1, Ordering::SeqCst);
+        let _ = self.sender.send(ConversionJob { id, file_path, format });
+    }
+}
@@ -1,46 +1,152 @@
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc;
+use crate::formats;
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
 
 pub struct FolderWatcher {
     watcher: RecommendedWatcher,
+    stop: Arc<AtomicBool>,
+    debounce_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        // The event-handling thread exits on its own once `self.watcher` is
+        // dropped below (notify drops its sender, closing `rx`), but the
+        // debounce flusher runs its own sleep loop and wouldn't otherwise
+        // notice — signal it explicitly so it doesn't outlive us.
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.debounce_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Tunables for [`FolderWatcher::new`].
+#[derive(Debug, Clone, Copy)]
+pub struct WatcherConfig {
+    /// Watch subdirectories in addition to the root folder.
+    pub recursive: bool,
+    /// Enqueue files that already exist in the folder when watching
+    /// starts, not just ones created or modified afterward.
+    pub include_existing: bool,
+    /// Coalesce rapid Create/Modify events for the same path within this
+    /// window so partially-written downloads aren't decrypted mid-copy.
+    pub debounce_ms: u64,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        WatcherConfig {
+            recursive: true,
+            include_existing: true,
+            debounce_ms: 1000,
+        }
+    }
 }
 
 impl FolderWatcher {
-    pub fn new<F>(path_str: String, callback: F) -> anyhow::Result<Self> 
-    where F: Fn(std::path::PathBuf) + Send + 'static
+    pub fn new<F>(path_str: String, config: WatcherConfig, callback: F) -> anyhow::Result<Self>
+    where
+        F: Fn(PathBuf) + Send + Sync + 'static,
     {
         let (tx, rx) = mpsc::channel();
-        
+
         // Initialize watcher
         let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
-        
+
         // Start watching
         let path = Path::new(&path_str);
-        watcher.watch(path, RecursiveMode::NonRecursive)?;
+        let recursive_mode = if config.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(path, recursive_mode)?;
+
+        let callback = Arc::new(callback);
+
+        if config.include_existing {
+            let walker = WalkDir::new(path).max_depth(if config.recursive { usize::MAX } else { 1 });
+            for entry in walker.into_iter().filter_map(|e| e.ok()) {
+                if entry.file_type().is_file() && formats::is_supported_extension(entry.path()) {
+                    callback(entry.into_path());
+                }
+            }
+        }
+
+        // Paths with a pending event, and when we last saw one for them.
+        let pending: Arc<Mutex<HashMap<PathBuf, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+        let debounce = Duration::from_millis(config.debounce_ms);
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Debounce flusher: fires the callback once a path has gone quiet
+        // for `debounce` instead of on every single Create/Modify event.
+        // Tied to `stop` so it exits promptly when the FolderWatcher does,
+        // instead of running forever as a leaked background thread.
+        let debounce_thread = {
+            let pending = pending.clone();
+            let callback = callback.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(100));
+
+                    let ready: Vec<PathBuf> = {
+                        let mut pending = pending.lock().unwrap();
+                        let now = Instant::now();
+                        let ready: Vec<PathBuf> = pending
+                            .iter()
+                            .filter(|(_, last_seen)| now.duration_since(**last_seen) >= debounce)
+                            .map(|(path, _)| path.clone())
+                            .collect();
+                        for path in &ready {
+                            pending.remove(path);
+                        }
+                        ready
+                    };
+
+                    for path in ready {
+                        callback(path);
+                    }
+                }
+            })
+        };
 
         // Spawn a thread to handle events
         thread::spawn(move || {
             for res in rx {
                 match res {
                     Ok(event) => {
-                        // We only care about Create events for .ncm files
-                        if let notify::EventKind::Create(_) = event.kind {
-                             for path in event.paths {
-                                 if let Some(ext) = path.extension() {
-                                     if ext == "ncm" {
-                                         callback(path);
-                                     }
-                                 }
-                             }
+                        // Create and Modify events both matter: Modify
+                        // catches files that land via a rename-after-write,
+                        // which is how many downloaders finish a copy.
+                        let is_relevant = matches!(
+                            event.kind,
+                            notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+                        );
+                        if is_relevant {
+                            for path in event.paths {
+                                if formats::is_supported_extension(&path) {
+                                    pending.lock().unwrap().insert(path, Instant::now());
+                                }
+                            }
                         }
-                    },
+                    }
                     Err(e) => println!("Watch error: {:?}", e),
                 }
             }
         });
 
-        Ok(FolderWatcher { watcher })
+        Ok(FolderWatcher {
+            watcher,
+            stop,
+            debounce_thread: Some(debounce_thread),
+        })
     }
 }